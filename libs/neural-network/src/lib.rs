@@ -1,15 +1,48 @@
-use std::iter::once;
-
+use nalgebra::{DMatrix, DVector};
 use rand::{Rng, RngCore};
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Network {
     layers: Vec<Layer>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LayerTopology {
     pub neurons: usize,
+    pub activation: Activation,
+}
+
+/// The non-linearity applied to a layer's outputs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Activation {
+    Relu,
+    Sigmoid,
+    Tanh,
+    Linear,
+}
+
+/// How a layer's weights are drawn when a network is randomized.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeightInit {
+    /// Each weight and bias sampled uniformly from `-1..=1`, regardless of layer width.
+    Uniform,
+    /// Weights drawn from `N(0, sqrt(2 / fan_in))`, biases zeroed. Suited to ReLU-style layers.
+    He,
+    /// Weights drawn from `N(0, sqrt(1 / fan_in))`, biases zeroed. Suited to saturating layers.
+    Xavier,
+}
+
+impl Activation {
+    fn apply(self, x: f32) -> f32 {
+        match self {
+            Activation::Relu => x.max(0.0),
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            Activation::Tanh => x.tanh(),
+            Activation::Linear => x,
+        }
+    }
 }
 
 impl Network {
@@ -19,24 +52,27 @@ impl Network {
             .fold(inputs, |inputs, layer| layer.propagate(inputs))
     }
 
-    pub fn random(rng: &mut dyn RngCore, layers: &[LayerTopology]) -> Self {
+    pub fn random(rng: &mut dyn RngCore, layers: &[LayerTopology], init: WeightInit) -> Self {
         assert!(layers.len() > 1);
 
         let layers = layers
             .windows(2)
-            .map(|layers_pair| Layer::random(rng, layers_pair[0].neurons, layers_pair[1].neurons))
+            .map(|layers_pair| {
+                Layer::random(
+                    rng,
+                    layers_pair[0].neurons,
+                    layers_pair[1].neurons,
+                    layers_pair[1].activation,
+                    init,
+                )
+            })
             .collect();
 
         Self { layers }
     }
 
     pub fn weights(&self) -> Vec<f32> {
-        self.layers
-            .iter()
-            .flat_map(|layer| layer.neurons.iter())
-            .flat_map(|neuron| once(&neuron.bias).chain(&neuron.weights))
-            .copied()
-            .collect()
+        self.layers.iter().flat_map(Layer::weights).collect()
     }
 
     pub fn from_weights(layers: &[LayerTopology], weights: impl IntoIterator<Item = f32>) -> Self {
@@ -46,7 +82,14 @@ impl Network {
 
         let layers = layers
             .windows(2)
-            .map(|layers| Layer::from_weights(layers[0].neurons, layers[1].neurons, &mut weights))
+            .map(|layers| {
+                Layer::from_weights(
+                    layers[0].neurons,
+                    layers[1].neurons,
+                    layers[1].activation,
+                    &mut weights,
+                )
+            })
             .collect();
 
         if weights.next().is_some() {
@@ -55,79 +98,173 @@ impl Network {
 
         Self { layers }
     }
+
+    /// Propagates `inputs` while recording every layer's output, returning `(per_layer_outputs,
+    /// final_output)`. Lets a renderer visualize the signal flowing through the network.
+    pub fn propagate_with_activations(&self, inputs: Vec<f32>) -> (Vec<Vec<f32>>, Vec<f32>) {
+        let mut activations = Vec::with_capacity(self.layers.len());
+        let mut current = inputs;
+
+        for layer in &self.layers {
+            current = layer.propagate(current);
+            activations.push(current.clone());
+        }
+
+        let outputs = activations.last().cloned().unwrap_or_default();
+
+        (activations, outputs)
+    }
+
+    /// Read-only traversal of the network's layers, for drawing the brain without exposing the
+    /// private `Layer` internals.
+    pub fn layers(&self) -> impl ExactSizeIterator<Item = LayerView<'_>> {
+        self.layers.iter().map(|layer| LayerView { layer })
+    }
+
+    /// Serializes the whole network - activations included - to a JSON string.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Rebuilds a network previously produced by [`Network::to_json`].
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
 }
 
-#[derive(Debug)]
+/// A single dense layer, stored GEMM-style: an `output x input` weight matrix and an `output` bias
+/// vector, so propagation is one matrix-vector product instead of a dot product per neuron.
+#[derive(Debug, Serialize, Deserialize)]
 struct Layer {
-    neurons: Vec<Neuron>,
+    weights: DMatrix<f32>,
+    bias: DVector<f32>,
+    activation: Activation,
 }
 
 impl Layer {
     fn propagate(&self, inputs: Vec<f32>) -> Vec<f32> {
-        self.neurons
-            .iter()
-            .map(|neuron| neuron.propagate(&inputs))
-            .collect()
+        assert_eq!(inputs.len(), self.weights.ncols());
+
+        let inputs = DVector::from_vec(inputs);
+        let outputs = &self.weights * inputs + &self.bias;
+
+        outputs.iter().map(|&x| self.activation.apply(x)).collect()
     }
 
-    fn random(rng: &mut dyn RngCore, input_size: usize, output_size: usize) -> Self {
-        let neurons = (0..output_size)
-            .map(|_| Neuron::random(rng, input_size))
-            .collect();
+    fn random(
+        rng: &mut dyn RngCore,
+        input_size: usize,
+        output_size: usize,
+        activation: Activation,
+        init: WeightInit,
+    ) -> Self {
+        let mut bias = DVector::zeros(output_size);
+        let mut weights = DMatrix::zeros(output_size, input_size);
+
+        match init {
+            // Draw bias-then-weights per output neuron so the sampling order - and therefore the
+            // flat `weights()` ordering - matches the historical scalar implementation.
+            WeightInit::Uniform => {
+                for neuron in 0..output_size {
+                    bias[neuron] = rng.random_range(-1.0..=1.0);
+
+                    for input in 0..input_size {
+                        weights[(neuron, input)] = rng.random_range(-1.0..=1.0);
+                    }
+                }
+            }
+            // He/Xavier scale the variance to the fan-in and leave the biases at zero.
+            WeightInit::He | WeightInit::Xavier => {
+                let gain = if init == WeightInit::He { 2.0 } else { 1.0 };
+                let std_dev = (gain / input_size as f32).sqrt();
+                let normal = Normal::new(0.0, std_dev).expect("invalid standard deviation");
+
+                for neuron in 0..output_size {
+                    for input in 0..input_size {
+                        weights[(neuron, input)] = normal.sample(rng);
+                    }
+                }
+            }
+        }
 
-        Self { neurons }
+        Self {
+            weights,
+            bias,
+            activation,
+        }
     }
 
     fn from_weights(
         input_size: usize,
         output_size: usize,
+        activation: Activation,
         weights: &mut dyn Iterator<Item = f32>,
     ) -> Self {
-        let neurons = (0..output_size)
-            .map(|_| Neuron::from_weights(input_size, weights))
-            .collect();
+        let mut bias = DVector::zeros(output_size);
+        let mut matrix = DMatrix::zeros(output_size, input_size);
+
+        for neuron in 0..output_size {
+            bias[neuron] = weights.next().expect("got not enough weights");
+
+            for input in 0..input_size {
+                matrix[(neuron, input)] = weights.next().expect("got not enough weights");
+            }
+        }
 
-        Self { neurons }
+        Self {
+            weights: matrix,
+            bias,
+            activation,
+        }
     }
-}
 
-#[derive(Debug)]
-struct Neuron {
-    bias: f32,
-    weights: Vec<f32>,
-}
+    /// Flattens this layer into the `[bias, weights...]` per-neuron ordering used by the
+    /// chromosome, walking the weight matrix row by row.
+    fn weights(&self) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.bias.len() + self.weights.len());
 
-impl Neuron {
-    fn propagate(&self, inputs: &[f32]) -> f32 {
-        assert_eq!(inputs.len(), self.weights.len());
+        for neuron in 0..self.weights.nrows() {
+            out.push(self.bias[neuron]);
 
-        let output = inputs
-            .iter()
-            .zip(&self.weights)
-            .map(|(&input, &weight)| input * weight)
-            .sum::<f32>();
+            for input in 0..self.weights.ncols() {
+                out.push(self.weights[(neuron, input)]);
+            }
+        }
 
-        (self.bias + output).max(0.0)
+        out
     }
+}
 
-    fn random(rng: &mut dyn RngCore, input_size: usize) -> Self {
-        let bias = rng.random_range(-1.0..=1.0);
+/// A read-only view over a single [`Layer`].
+pub struct LayerView<'a> {
+    layer: &'a Layer,
+}
 
-        let weights = (0..input_size)
-            .map(|_| rng.random_range(-1.0..=1.0))
-            .collect();
+impl<'a> LayerView<'a> {
+    pub fn activation(&self) -> Activation {
+        self.layer.activation
+    }
 
-        Self { bias, weights }
+    pub fn neurons(&self) -> impl ExactSizeIterator<Item = NeuronView<'a>> {
+        let layer = self.layer;
+
+        (0..layer.weights.nrows()).map(move |index| NeuronView { layer, index })
     }
+}
 
-    fn from_weights(input_size: usize, weights: &mut (dyn Iterator<Item = f32>)) -> Neuron {
-        let bias = weights.next().expect("got not enough weights");
+/// A read-only view over a single neuron within a [`Layer`].
+pub struct NeuronView<'a> {
+    layer: &'a Layer,
+    index: usize,
+}
 
-        let weights = (0..input_size)
-            .map(|_| weights.next().expect("got not enough weights"))
-            .collect();
+impl NeuronView<'_> {
+    pub fn bias(&self) -> f32 {
+        self.layer.bias[self.index]
+    }
 
-        Self { bias, weights }
+    pub fn weights(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        (0..self.layer.weights.ncols()).map(move |input| self.layer.weights[(self.index, input)])
     }
 }
 
@@ -138,68 +275,71 @@ mod tests {
     use rand::SeedableRng;
     use rand_chacha::ChaCha8Rng;
 
-    // Neuron tests
-    #[test]
-    fn random_neuron() {
-        let mut rng = ChaCha8Rng::from_seed(Default::default());
-        let neuron = Neuron::random(&mut rng, 4);
+    /// Builds a layer from `(bias, weights)` rows, one row per output neuron.
+    fn layer(rows: &[(f32, &[f32])], activation: Activation) -> Layer {
+        let output_size = rows.len();
+        let input_size = rows[0].1.len();
 
-        assert_relative_eq!(neuron.bias, -0.6255188);
-        assert_relative_eq!(
-            neuron.weights.as_slice(),
-            [0.67383933, 0.81812596, 0.26284885, 0.5238805].as_ref()
-        );
-    }
+        let mut bias = DVector::zeros(output_size);
+        let mut weights = DMatrix::zeros(output_size, input_size);
 
-    #[test]
-    fn propagate_neuron() {
-        let neuron = Neuron {
-            bias: 0.5,
-            weights: vec![-0.3, 0.8],
-        };
+        for (neuron, &(b, w)) in rows.iter().enumerate() {
+            bias[neuron] = b;
 
-        assert_relative_eq!(neuron.propagate(&[-10.0, -10.0]), 0.0,);
+            for (input, &weight) in w.iter().enumerate() {
+                weights[(neuron, input)] = weight;
+            }
+        }
 
-        assert_relative_eq!(
-            neuron.propagate(&[0.5, 1.0]),
-            (-0.3 * 0.5) + (0.8 * 1.0) + 0.5,
-        );
+        Layer {
+            weights,
+            bias,
+            activation,
+        }
     }
 
     // Layer tests
     #[test]
     fn random_layer() {
         let mut rng = ChaCha8Rng::from_seed(Default::default());
-        let layer = Layer::random(&mut rng, 3, 2);
+        let layer = Layer::random(&mut rng, 3, 2, Activation::Relu, WeightInit::Uniform);
 
-        // test all elements of first neuron
-        assert_relative_eq!(layer.neurons[0].bias, -0.6255188);
         assert_relative_eq!(
-            layer.neurons[0].weights.as_slice(),
-            [0.67383933, 0.81812596, 0.26284885].as_ref()
+            layer.weights().as_slice(),
+            [
+                -0.6255188, 0.67383933, 0.81812596, 0.26284885, // neuron 0
+                0.5238805, -0.5351684, 0.0693696, -0.7648182, // neuron 1
+            ]
+            .as_ref()
         );
+    }
 
-        assert_relative_eq!(layer.neurons[1].bias, 0.5238805);
-        assert_relative_eq!(
-            layer.neurons[1].weights.as_slice(),
-            [-0.5351684, 0.0693696, -0.7648182].as_ref()
-        )
+    #[test]
+    fn he_init_variance() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        // A wide fan-in so the empirical variance is a tight estimate of the target.
+        let fan_in = 1024;
+        let layer = Layer::random(&mut rng, fan_in, 1024, Activation::Relu, WeightInit::He);
+
+        // Biases are left at zero under He/Xavier.
+        assert_relative_eq!(layer.bias.as_slice(), DVector::zeros(1024).as_slice());
+
+        let weights = layer.weights.as_slice();
+        let mean = weights.iter().sum::<f32>() / weights.len() as f32;
+        let variance =
+            weights.iter().map(|w| (w - mean).powi(2)).sum::<f32>() / weights.len() as f32;
+
+        let target = 2.0 / fan_in as f32;
+        assert_relative_eq!(variance, target, epsilon = target * 0.1);
     }
 
     #[test]
     fn propagate_layer() {
-        let layer = Layer {
-            neurons: vec![
-                Neuron {
-                    bias: 0.5,
-                    weights: vec![-0.3, 0.8],
-                },
-                Neuron {
-                    bias: 0.2,
-                    weights: vec![0.2, -0.4],
-                },
-            ],
-        };
+        let layer = layer(
+            &[(0.5, &[-0.3, 0.8]), (0.2, &[0.2, -0.4])],
+            Activation::Relu,
+        );
 
         assert_relative_eq!(
             layer.propagate(vec!(-10.0, -10.0)).as_slice(),
@@ -215,6 +355,36 @@ mod tests {
         );
     }
 
+    /// The matrix path must agree with a hand-rolled scalar dot-product on a fixed network.
+    #[test]
+    fn matrix_matches_scalar() {
+        let rows0 = [(0.5_f32, [-0.3_f32, 0.8].as_slice()), (0.2, [0.2, -0.4].as_slice())];
+        let rows1 = [(0.4_f32, [-0.7_f32, 0.9].as_slice())];
+
+        let network = Network {
+            layers: vec![
+                layer(&rows0, Activation::Relu),
+                layer(&rows1, Activation::Relu),
+            ],
+        };
+
+        let scalar = |rows: &[(f32, &[f32])], inputs: &[f32]| -> Vec<f32> {
+            rows
+                .iter()
+                .map(|&(bias, weights)| {
+                    let sum: f32 = inputs.iter().zip(weights).map(|(&i, &w)| i * w).sum();
+                    (bias + sum).max(0.0)
+                })
+                .collect()
+        };
+
+        let inputs = vec![0.6, -0.9];
+        let hidden = scalar(&rows0, &inputs);
+        let expected = scalar(&rows1, &hidden);
+
+        assert_relative_eq!(network.propagate(inputs).as_slice(), expected.as_slice());
+    }
+
     // Tests for Network
     #[test]
     fn random_network() {
@@ -222,40 +392,34 @@ mod tests {
         let network = Network::random(
             &mut rng,
             &[
-                LayerTopology { neurons: 4 },
-                LayerTopology { neurons: 3 },
-                LayerTopology { neurons: 2 },
+                LayerTopology {
+                    neurons: 4,
+                    activation: Activation::Relu,
+                },
+                LayerTopology {
+                    neurons: 3,
+                    activation: Activation::Relu,
+                },
+                LayerTopology {
+                    neurons: 2,
+                    activation: Activation::Relu,
+                },
             ],
-        );
-
-        println!("{:?}", network);
-        assert_relative_eq!(network.layers[0].neurons[0].bias, -0.6255188);
-        assert_relative_eq!(network.layers[0].neurons[1].bias, -0.5351684);
-        assert_relative_eq!(network.layers[0].neurons[2].bias, -0.19277143);
-
-        assert_relative_eq!(network.layers[1].neurons[0].bias, -0.4766221);
-        assert_relative_eq!(network.layers[1].neurons[1].bias, 0.35662675);
-
-        assert_relative_eq!(
-            network.layers[0].neurons[0].weights.as_slice(),
-            [0.67383933, 0.81812596, 0.26284885, 0.5238805].as_ref()
-        );
-        assert_relative_eq!(
-            network.layers[0].neurons[1].weights.as_slice(),
-            [0.069369555, -0.7648182, -0.102499485, -0.48879623].as_ref()
-        );
-        assert_relative_eq!(
-            network.layers[0].neurons[2].weights.as_slice(),
-            [-0.8020501, 0.27546048, -0.98680043, 0.4452355].as_ref()
+            WeightInit::Uniform,
         );
 
         assert_relative_eq!(
-            network.layers[1].neurons[0].weights.as_slice(),
-            [-0.89078736, -0.36127806, -0.14956546].as_ref()
-        );
-        assert_relative_eq!(
-            network.layers[1].neurons[1].weights.as_slice(),
-            [-0.8566594, 0.3330984, 0.11767411].as_ref(),
+            network.weights().as_slice(),
+            [
+                // hidden layer (4 -> 3)
+                -0.6255188, 0.67383933, 0.81812596, 0.26284885, 0.5238805,
+                -0.5351684, 0.069369555, -0.7648182, -0.102499485, -0.48879623,
+                -0.19277143, -0.8020501, 0.27546048, -0.98680043, 0.4452355,
+                // output layer (3 -> 2)
+                -0.4766221, -0.89078736, -0.36127806, -0.14956546,
+                0.35662675, -0.8566594, 0.3330984, 0.11767411,
+            ]
+            .as_ref()
         );
     }
 
@@ -263,24 +427,11 @@ mod tests {
     fn propagate_network() {
         let network = Network {
             layers: vec![
-                Layer {
-                    neurons: vec![
-                        Neuron {
-                            bias: 0.5,
-                            weights: vec![-0.3, 0.8],
-                        },
-                        Neuron {
-                            bias: 0.2,
-                            weights: vec![0.2, -0.4],
-                        },
-                    ],
-                },
-                Layer {
-                    neurons: vec![Neuron {
-                        bias: 0.4,
-                        weights: vec![-0.7, 0.9],
-                    }],
-                },
+                layer(
+                    &[(0.5, &[-0.3, 0.8]), (0.2, &[0.2, -0.4])],
+                    Activation::Relu,
+                ),
+                layer(&[(0.4, &[-0.7, 0.9])], Activation::Relu),
             ],
         };
 
@@ -301,18 +452,8 @@ mod tests {
     fn weights() {
         let network = Network {
             layers: vec![
-                Layer {
-                    neurons: vec![Neuron {
-                        bias: 0.1,
-                        weights: vec![0.2, 0.3, 0.4],
-                    }],
-                },
-                Layer {
-                    neurons: vec![Neuron {
-                        bias: 0.5,
-                        weights: vec![0.6, 0.7, 0.8],
-                    }],
-                },
+                layer(&[(0.1, &[0.2, 0.3, 0.4])], Activation::Relu),
+                layer(&[(0.5, &[0.6, 0.7, 0.8])], Activation::Relu),
             ],
         };
 
@@ -324,7 +465,16 @@ mod tests {
 
     #[test]
     fn from_weights() {
-        let layers = &[LayerTopology { neurons: 3 }, LayerTopology { neurons: 2 }];
+        let layers = &[
+            LayerTopology {
+                neurons: 3,
+                activation: Activation::Relu,
+            },
+            LayerTopology {
+                neurons: 2,
+                activation: Activation::Relu,
+            },
+        ];
 
         let weights = vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
         let network = Network::from_weights(layers, weights.clone());
@@ -332,4 +482,66 @@ mod tests {
 
         assert_relative_eq!(actual.as_slice(), weights.as_slice());
     }
+
+    #[test]
+    fn json_round_trip() {
+        let network = Network {
+            layers: vec![
+                layer(&[(0.1, &[0.2, 0.3])], Activation::Tanh),
+                layer(&[(-0.4, &[0.5])], Activation::Sigmoid),
+            ],
+        };
+
+        let json = network.to_json().unwrap();
+        let restored = Network::from_json(&json).unwrap();
+
+        let inputs = vec![0.7, -0.2];
+        assert_relative_eq!(
+            restored.propagate(inputs.clone()).as_slice(),
+            network.propagate(inputs).as_slice()
+        );
+    }
+
+    #[test]
+    fn layers_view() {
+        let network = Network {
+            layers: vec![
+                layer(&[(0.1, &[0.2, 0.3])], Activation::Tanh),
+                layer(&[(-0.4, &[0.5])], Activation::Sigmoid),
+            ],
+        };
+
+        let views: Vec<_> = network.layers().collect();
+        assert_eq!(views.len(), 2);
+        assert_eq!(views[0].activation(), Activation::Tanh);
+        assert_eq!(views[1].activation(), Activation::Sigmoid);
+
+        let neurons: Vec<_> = views[0].neurons().collect();
+        assert_eq!(neurons.len(), 1);
+        assert_relative_eq!(neurons[0].bias(), 0.1);
+        assert_relative_eq!(
+            neurons[0].weights().collect::<Vec<_>>().as_slice(),
+            [0.2, 0.3].as_ref()
+        );
+    }
+
+    #[test]
+    fn propagate_with_activations() {
+        let network = Network {
+            layers: vec![
+                layer(&[(0.5, &[-0.3, 0.8]), (0.2, &[0.2, -0.4])], Activation::Relu),
+                layer(&[(0.4, &[-0.7, 0.9])], Activation::Relu),
+            ],
+        };
+
+        let inputs = vec![-0.7, -0.8];
+        let (per_layer, outputs) = network.propagate_with_activations(inputs.clone());
+
+        assert_eq!(per_layer.len(), 2);
+        assert_relative_eq!(outputs.as_slice(), network.propagate(inputs).as_slice());
+        assert_relative_eq!(
+            per_layer.last().unwrap().as_slice(),
+            outputs.as_slice()
+        );
+    }
 }
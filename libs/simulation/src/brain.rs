@@ -1,20 +1,93 @@
 use crate::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 pub struct Brain {
     pub(crate) nn: nn::Network,
+    pub(crate) config: BrainConfig,
+}
+
+/// Tunable shape of a [`Brain`]'s network. The chromosome length is derived from this, so it has
+/// to be stored alongside the population and reused when brains are rebuilt from chromosomes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BrainConfig {
+    /// Sizes of the hidden layers, in order. Empty means "use the historical default" of a single
+    /// hidden layer twice as wide as the eye.
+    pub hidden_layers: Vec<usize>,
+    /// Activation applied to every hidden layer.
+    pub hidden_activation: nn::Activation,
+    /// Activation applied to the motor output layer.
+    pub output_activation: nn::Activation,
+    /// How the initial random weights are drawn.
+    pub weight_init: nn::WeightInit,
+}
+
+impl Default for BrainConfig {
+    fn default() -> Self {
+        Self {
+            hidden_layers: Vec::new(),
+            hidden_activation: nn::Activation::Tanh,
+            output_activation: nn::Activation::Sigmoid,
+            weight_init: nn::WeightInit::Uniform,
+        }
+    }
+}
+
+/// A frozen brain together with the topology that produced it, so the network can be reloaded and
+/// inspected without access to the originating eye.
+#[derive(Debug, Deserialize)]
+struct BrainSave {
+    #[allow(dead_code)]
+    topology: Vec<nn::LayerTopology>,
+    config: BrainConfig,
+    network: nn::Network,
+}
+
+/// Borrowed counterpart of [`BrainSave`] used while serializing, to avoid cloning the network.
+#[derive(Serialize)]
+struct BrainSaveRef<'a> {
+    topology: Vec<nn::LayerTopology>,
+    config: &'a BrainConfig,
+    network: &'a nn::Network,
 }
 
 impl Brain {
-    pub fn random(rng: &mut dyn RngCore, eye: &Eye) -> Self {
+    pub fn random(rng: &mut dyn RngCore, eye: &Eye, config: BrainConfig) -> Self {
         Self {
-            nn: nn::Network::random(rng, &Self::topology(eye)),
+            nn: nn::Network::random(rng, &Self::topology(eye, &config), config.weight_init),
+            config,
         }
     }
 
-    pub(crate) fn from_chromosome(chromosome: ga::Chromosome, eye: &Eye) -> Self {
+    /// Freezes this brain - network and topology - to a JSON string for later reloading.
+    pub fn to_json(&self, eye: &Eye) -> serde_json::Result<String> {
+        let save = BrainSaveRef {
+            topology: Self::topology(eye, &self.config),
+            config: &self.config,
+            network: &self.nn,
+        };
+
+        serde_json::to_string(&save)
+    }
+
+    /// Reloads a brain previously frozen with [`Brain::to_json`].
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let save: BrainSave = serde_json::from_str(json)?;
+
+        Ok(Self {
+            nn: save.network,
+            config: save.config,
+        })
+    }
+
+    pub(crate) fn from_chromosome(
+        chromosome: ga::Chromosome,
+        eye: &Eye,
+        config: BrainConfig,
+    ) -> Self {
         Self {
-            nn: nn::Network::from_weights(&Self::topology(eye), chromosome),
+            nn: nn::Network::from_weights(&Self::topology(eye, &config), chromosome),
+            config,
         }
     }
 
@@ -22,19 +95,37 @@ impl Brain {
         self.nn.weights().into_iter().collect()
     }
 
-    fn topology(eye: &Eye) -> [nn::LayerTopology; 3] {
-        // The input Layer that takes in what the eyes see
-        [
-            nn::LayerTopology {
-                neurons: eye.cells(),
-            },
-            // The hidden Layer(s) that mutate. Start with one with more nodes than input
-            nn::LayerTopology {
-                neurons: 2 * eye.cells(),
-            },
-            // The Output Layer; since the brain will control the bird's speed and rotation, we
-            // need two numbers = two neurons
-            nn::LayerTopology { neurons: 2 },
-        ]
+    fn topology(eye: &Eye, config: &BrainConfig) -> Vec<nn::LayerTopology> {
+        // The hidden widths come from the config; an empty config falls back to the historical
+        // single hidden layer twice as wide as the eye.
+        let hidden: Vec<usize> = if config.hidden_layers.is_empty() {
+            vec![2 * eye.cells()]
+        } else {
+            config.hidden_layers.clone()
+        };
+
+        // The input Layer that takes in what the eyes see; its activation is unused since no
+        // neurons feed into it
+        let mut topology = vec![nn::LayerTopology {
+            neurons: eye.cells(),
+            activation: nn::Activation::Linear,
+        }];
+
+        // The hidden Layer(s) that mutate, using the configured hidden activation (Tanh by
+        // default, which saturates for smoother signals)
+        topology.extend(hidden.into_iter().map(|neurons| nn::LayerTopology {
+            neurons,
+            activation: config.hidden_activation,
+        }));
+
+        // The Output Layer; since the brain will control the bird's speed and rotation, we need
+        // two numbers = two neurons, passed through the configured output activation (Sigmoid by
+        // default, which bounds them to 0..1)
+        topology.push(nn::LayerTopology {
+            neurons: 2,
+            activation: config.output_activation,
+        });
+
+        topology
     }
 }
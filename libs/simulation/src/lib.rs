@@ -31,10 +31,18 @@ pub struct Simulation {
     world: World,
     ga: ga::GeneticAlgorithm<ga::RouletteWheelSelection>,
     age: usize,
+    /// Shared brain shape, reused whenever the population is rebuilt from chromosomes.
+    brain_config: BrainConfig,
 }
 
 impl Simulation {
     pub fn random(rng: &mut dyn RngCore) -> Self {
+        Self::from_config(rng, BrainConfig::default())
+    }
+
+    /// Builds a simulation whose animals are wired from the given [`BrainConfig`], letting a
+    /// caller experiment with deeper or wider networks without recompiling.
+    pub fn from_config(rng: &mut dyn RngCore, brain_config: BrainConfig) -> Self {
         let ga = ga::GeneticAlgorithm::new(
             ga::RouletteWheelSelection,
             ga::UniformCrossover,
@@ -42,9 +50,12 @@ impl Simulation {
         );
 
         Self {
-            world: World::random(rng),
+            // Generation 0 has to use the same topology `evolve` will later rebuild from, otherwise
+            // its chromosomes would be sized for a different network and `from_weights` would panic.
+            world: World::random(rng, brain_config.clone()),
             ga,
             age: 0,
+            brain_config,
         }
     }
 
@@ -128,7 +139,7 @@ impl Simulation {
 
         self.world.animals = evolved_population
             .into_iter()
-            .map(|individual| individual.into_animal(rng))
+            .map(|individual| individual.into_animal(rng, self.brain_config.clone()))
             .collect();
 
         // Restarting food, mostly for visual purposes to spot when evolution happens